@@ -0,0 +1,766 @@
+use malrs::core::{apply_function, ns};
+use malrs::env::Env;
+use malrs::printer::pr_str;
+use malrs::reader::read_str;
+use malrs::readline::Readline;
+use malrs::types::MalValueType::{List, MalFunc, Map, Nil, Str, Symbol, Vector};
+use malrs::types::{MalError, MalMap, MalResult, MalValue, MalVector};
+use malrs::types::{MalList, MalValueType};
+use std::env;
+use std::iter::once;
+use std::rc::Rc;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut env = create_root_env(&args[1..]);
+
+    rep("(def! not (fn* (a) (if a false true)))", &mut env).unwrap();
+    rep(
+        "(def! load-file (fn* (f) (eval (read-string (str \"(do \" (slurp f) \"\\nnil)\")))))",
+        &mut env,
+    )
+    .unwrap();
+
+    if let Some(script) = args.get(1) {
+        match rep(&format!("(load-file \"{}\")", script), &mut env) {
+            Ok(_) => {}
+            Err(mal_error) => println!("Error! {}", mal_error),
+        }
+        return;
+    }
+
+    let mut readline = Readline::new();
+
+    loop {
+        match readline.readline() {
+            None => break,
+            Some(line) => {
+                if !line.is_empty() {
+                    match rep(&line, &mut env) {
+                        Ok(result) => println!("{}", result),
+                        Err(MalError::EmptyProgram) => {}
+                        Err(mal_error) => println!("Error! {}", mal_error),
+                    }
+                }
+            }
+        }
+    }
+
+    readline.save_history();
+}
+
+fn create_root_env(script_args: &[String]) -> Env {
+    let mut env = Env::new().with_eval_fn(Rc::new(eval));
+
+    for (name, val) in ns(&env) {
+        env.set(name, val);
+    }
+
+    let argv = script_args
+        .iter()
+        .skip(1)
+        .map(|arg| MalValue::new(Str(arg.clone())))
+        .collect();
+    env.set("*ARGV*", MalValue::new_list(argv));
+
+    env
+}
+
+fn rep(s: &str, env: &mut Env) -> Result<String, MalError> {
+    let read_val = read(s)?;
+    let eval_val = eval(&read_val, env)?;
+    Ok(print(&eval_val))
+}
+
+fn read(s: &str) -> MalResult {
+    read_str(s)
+}
+
+fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
+    let mut ast = ast.clone();
+    let mut env = env.clone();
+
+    'tco: loop {
+        ast = macroexpand(&ast, &env)?;
+
+        match *ast.mal_type {
+            List(ref mal_list) if mal_list.vec.is_empty() => return Ok(ast.clone()),
+            List(MalList { vec: ref list, .. }) => {
+                let first_arg = &list[0];
+
+                match *first_arg.mal_type {
+                    Symbol(ref name) if name == "def!" => {
+                        return apply_special_form_def(&list[1..], &mut env)
+                    }
+                    Symbol(ref name) if name == "defmacro!" => {
+                        return apply_special_form_defmacro(&list[1..], &mut env)
+                    }
+                    Symbol(ref name) if name == "macroexpand" => {
+                        return apply_special_form_macroexpand(&list[1..], &env)
+                    }
+                    Symbol(ref name) if name == "let*" => {
+                        let (inner_env, body) = apply_special_form_let(&list[1..], &env)?;
+                        env = inner_env;
+                        ast = body;
+                        continue 'tco;
+                    }
+                    Symbol(ref name) if name == "fn*" => {
+                        return apply_special_form_fn(&list[1..], &env)
+                    }
+                    Symbol(ref name) if name == "do" => {
+                        ast = apply_special_form_do(&list[1..], &mut env)?;
+                        continue 'tco;
+                    }
+                    Symbol(ref name) if name == "if" => {
+                        match apply_special_form_if(&list[1..], &mut env)? {
+                            Some(branch) => {
+                                ast = branch;
+                                continue 'tco;
+                            }
+                            None => return Ok(MalValue::nil()),
+                        }
+                    }
+                    Symbol(ref name) if name == "quote" => {
+                        return apply_special_form_quote(&list[1..])
+                    }
+                    Symbol(ref name) if name == "quasiquote" => {
+                        ast = apply_special_form_quasiquote(&list[1..])?;
+                        continue 'tco;
+                    }
+                    Symbol(ref name) if name == "try*" => {
+                        return apply_special_form_try(&list[1..], &mut env)
+                    }
+                    _ => {
+                        let evaluated_list_ast = eval_ast(&ast, &mut env)?;
+                        match *evaluated_list_ast.mal_type {
+                            List(MalList {
+                                vec: ref evaluated_list,
+                                ..
+                            }) => {
+                                let func = evaluated_list
+                                    .get(0)
+                                    .expect("Evaluation of non-empty list resulted in empty list.");
+
+                                match *func.mal_type {
+                                    MalFunc(ref mal_func) => {
+                                        let func_env = Env::with_binds(
+                                            Some(&mal_func.outer_env),
+                                            &mal_func.parameters,
+                                            &evaluated_list[1..],
+                                        )?;
+                                        env = func_env;
+                                        ast = mal_func.body.clone();
+                                        continue 'tco;
+                                    }
+                                    _ => return apply_function(func, &evaluated_list[1..], &mut env),
+                                }
+                            }
+                            _ => panic!(
+                                "Evaluation of list resulted in non-list: {:?}",
+                                evaluated_list_ast
+                            ),
+                        }
+                    }
+                }
+            }
+            _ => return eval_ast(&ast, &mut env),
+        }
+    }
+}
+
+fn print(mal_val: &MalValue) -> String {
+    pr_str(mal_val, true)
+}
+
+fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
+    match *ast.mal_type {
+        Symbol(ref s) => env.get(&s),
+        List(MalList { vec: ref list, .. }) => Ok(MalValue::new_list(eval_ast_seq(list, env)?)),
+        Vector(ref mal_vec) => Ok(MalValue::new_vector(eval_ast_seq(&mal_vec.vec, env)?)),
+        Map(ref mal_map) => eval_map(mal_map, env),
+        _ => Ok(ast.clone()),
+    }
+}
+
+fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
+    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+}
+
+fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
+    let map_args: Result<Vec<_>, _> = mal_map
+        .iter()
+        .flat_map(|(key, val)| once(Ok(key.clone())).chain(once(eval(val, env))))
+        .collect();
+
+    Ok(MalValue::new(Map(MalMap::from_arguments(
+        map_args?.as_slice(),
+    )?)))
+}
+
+fn apply_special_form_def(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "def! expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let arg1 = if let Symbol(ref symbol) = *args[0].mal_type {
+        Ok(symbol)
+    } else {
+        Err(MalError::SpecialForm(
+            "def! first argument must be a valid symbol name".to_string(),
+        ))
+    }?;
+
+    let arg2 = eval(&args[1], env)?;
+
+    env.set(arg1.as_str(), arg2.clone());
+
+    Ok(arg2)
+}
+
+/// Builds the inner `Env` for a `let*` form and returns it along with the body
+/// to evaluate in tail position, instead of evaluating the body itself.
+fn apply_special_form_let(args: &[MalValue], env: &Env) -> Result<(Env, MalValue), MalError> {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "let* expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let bindings = match *args[0].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings.as_slice()),
+        _ => Err(MalError::SpecialForm(
+            "let* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    if bindings.len() % 2 != 0 {
+        return Err(MalError::SpecialForm(
+            "let* bindings list must have an even number of elements".to_string(),
+        ));
+    }
+
+    let mut inner_env = Env::with_outer_env(env);
+
+    for i in (0..bindings.len()).step_by(2) {
+        let binding_name = if let Symbol(ref symbol) = *bindings[i].mal_type {
+            Ok(symbol)
+        } else {
+            Err(MalError::SpecialForm(
+                "let* odd numbered elements of binding list must be valid symbol names".to_string(),
+            ))
+        }?;
+
+        let binding_expr = eval(&bindings[i + 1], &mut inner_env)?;
+
+        inner_env.set(binding_name.as_str(), binding_expr);
+    }
+
+    Ok((inner_env, args[1].clone()))
+}
+
+fn apply_special_form_fn(args: &[MalValue], env: &Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "fn* expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let bindings = match *args[0].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings.as_slice()),
+        _ => Err(MalError::SpecialForm(
+            "fn* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    let parameters: Result<Vec<String>, _> = bindings
+        .iter()
+        .map(|val| {
+            if let Symbol(ref symbol) = *val.mal_type {
+                Ok(symbol.clone())
+            } else {
+                Err(MalError::SpecialForm(
+                    "fn*! first argument must be a sequence of valid symbol names".to_string(),
+                ))
+            }
+        })
+        .collect();
+
+    Ok(MalValue::new_mal_func(
+        args[1].clone(),
+        parameters?,
+        env.clone(),
+    ))
+}
+
+/// Evaluates all but the last form of a `do` body and returns the last form
+/// unevaluated, so the caller can continue the trampoline loop in tail position.
+fn apply_special_form_do(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.is_empty() {
+        return Ok(MalValue::nil());
+    }
+
+    for expr in &args[..args.len() - 1] {
+        eval(expr, env)?;
+    }
+
+    Ok(args[args.len() - 1].clone())
+}
+
+/// Evaluates the `if` condition and returns the branch to evaluate in tail
+/// position, or `None` when there's no else-branch and the condition is falsy.
+fn apply_special_form_if(args: &[MalValue], env: &mut Env) -> Result<Option<MalValue>, MalError> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(MalError::SpecialForm(format!(
+            "if expected 2 or 3 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let test_result = eval(&args[0], env)?;
+
+    match *test_result.mal_type {
+        MalValueType::False | Nil => {
+            if args.len() == 3 {
+                Ok(Some(args[2].clone()))
+            } else {
+                Ok(None)
+            }
+        }
+        _ => Ok(Some(args[1].clone())),
+    }
+}
+
+fn apply_special_form_defmacro(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "defmacro! expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let arg1 = if let Symbol(ref symbol) = *args[0].mal_type {
+        Ok(symbol)
+    } else {
+        Err(MalError::SpecialForm(
+            "defmacro! first argument must be a valid symbol name".to_string(),
+        ))
+    }?;
+
+    let arg2 = eval(&args[1], env)?;
+
+    let macro_val = if let MalFunc(ref mal_func) = *arg2.mal_type {
+        MalValue::new_mal_macro(
+            mal_func.body.clone(),
+            mal_func.parameters.clone(),
+            mal_func.outer_env.clone(),
+        )
+    } else {
+        return Err(MalError::SpecialForm(
+            "defmacro! second argument must evaluate to a function".to_string(),
+        ));
+    };
+
+    env.set(arg1.as_str(), macro_val.clone());
+
+    Ok(macro_val)
+}
+
+fn apply_special_form_macroexpand(args: &[MalValue], env: &Env) -> MalResult {
+    if args.len() != 1 {
+        return Err(MalError::SpecialForm(format!(
+            "macroexpand expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+
+    macroexpand(&args[0], env)
+}
+
+/// Returns the macro function bound to `ast`'s head symbol, if `ast` is a
+/// call to a symbol that's currently bound to a macro.
+fn macro_call_func(ast: &MalValue, env: &Env) -> Option<MalValue> {
+    let list = if let List(MalList { vec: ref list, .. }) = *ast.mal_type {
+        list
+    } else {
+        return None;
+    };
+
+    let name = if let Some(Symbol(ref name)) = list.get(0).map(|v| &*v.mal_type) {
+        name
+    } else {
+        return None;
+    };
+
+    let func = env.get(name).ok()?;
+
+    if let MalFunc(ref mal_func) = *func.mal_type {
+        if mal_func.is_macro {
+            return Some(func.clone());
+        }
+    }
+
+    None
+}
+
+/// Repeatedly expands `ast` while its head symbol is bound to a macro,
+/// calling the macro with the unevaluated argument forms each time.
+fn macroexpand(ast: &MalValue, env: &Env) -> MalResult {
+    let mut ast = ast.clone();
+
+    while let Some(func) = macro_call_func(&ast, env) {
+        let args = if let List(MalList { vec: ref list, .. }) = *ast.mal_type {
+            list[1..].to_vec()
+        } else {
+            unreachable!()
+        };
+
+        let mal_func = if let MalFunc(ref mal_func) = *func.mal_type {
+            mal_func
+        } else {
+            unreachable!()
+        };
+
+        let mut func_env =
+            Env::with_binds(Some(&mal_func.outer_env), &mal_func.parameters, &args)?;
+        ast = eval(&mal_func.body, &mut func_env)?;
+    }
+
+    Ok(ast)
+}
+
+/// Evaluates `(try* A (catch* B C))`: runs `A`, and on any error binds the
+/// error value to `B` in a child `Env` and evaluates `C`. Native (non-`throw`)
+/// errors are converted to a Mal string so `C` can still inspect them.
+fn apply_special_form_try(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "try* expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let (catch_symbol, catch_body) = parse_catch_form(&args[1])?;
+
+    match eval(&args[0], env) {
+        Ok(result) => Ok(result),
+        Err(MalError::Exception(exc_val)) => {
+            let mut inner_env = Env::with_outer_env(env);
+            inner_env.set(catch_symbol.as_str(), exc_val);
+            eval(&catch_body, &mut inner_env)
+        }
+        Err(other_err) => {
+            let mut inner_env = Env::with_outer_env(env);
+            inner_env.set(
+                catch_symbol.as_str(),
+                MalValue::new(Str(other_err.to_string())),
+            );
+            eval(&catch_body, &mut inner_env)
+        }
+    }
+}
+
+fn parse_catch_form(catch_form: &MalValue) -> Result<(String, MalValue), MalError> {
+    if let List(MalList { vec: ref list, .. }) = *catch_form.mal_type {
+        if let [ref head, ref symbol, ref body] = list[..] {
+            if let (Symbol(ref head_name), Symbol(ref symbol_name)) =
+                (&*head.mal_type, &*symbol.mal_type)
+            {
+                if head_name == "catch*" {
+                    return Ok((symbol_name.clone(), body.clone()));
+                }
+            }
+        }
+    }
+
+    Err(MalError::SpecialForm(
+        "try* second argument must be a (catch* symbol body) form".to_string(),
+    ))
+}
+
+fn apply_special_form_quote(args: &[MalValue]) -> MalResult {
+    if args.len() != 1 {
+        return Err(MalError::SpecialForm(format!(
+            "quote expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+
+    Ok(args[0].clone())
+}
+
+fn apply_special_form_quasiquote(args: &[MalValue]) -> MalResult {
+    if args.len() != 1 {
+        return Err(MalError::SpecialForm(format!(
+            "quasiquote expected 1 argument, got {}",
+            args.len()
+        )));
+    }
+
+    Ok(quasiquote(&args[0]))
+}
+
+/// Implements the `quasiquote` transform: `~x` becomes `x`, `~@x` inside a
+/// sequence splices `x` in with `concat`, and everything else is rebuilt with
+/// `cons` so the result can be evaluated like any other form.
+fn quasiquote(ast: &MalValue) -> MalValue {
+    match *ast.mal_type {
+        List(MalList { vec: ref list, .. }) if is_unquote(list) => list[1].clone(),
+        List(MalList { vec: ref list, .. }) | Vector(MalVector { vec: ref list, .. }) => {
+            quasiquote_seq(list)
+        }
+        Symbol(_) | Map(_) => new_call("quote", vec![ast.clone()]),
+        _ => ast.clone(),
+    }
+}
+
+fn quasiquote_seq(list: &[MalValue]) -> MalValue {
+    let mut result = MalValue::new_list(Vec::new());
+
+    for elt in list.iter().rev() {
+        result = match *elt.mal_type {
+            List(MalList { vec: ref sub, .. }) if is_splice_unquote(sub) => {
+                new_call("concat", vec![sub[1].clone(), result])
+            }
+            _ => new_call("cons", vec![quasiquote(elt), result]),
+        };
+    }
+
+    result
+}
+
+fn is_unquote(list: &[MalValue]) -> bool {
+    match list.get(0).map(|v| &*v.mal_type) {
+        Some(Symbol(ref name)) => name == "unquote" && list.len() == 2,
+        _ => false,
+    }
+}
+
+fn is_splice_unquote(list: &[MalValue]) -> bool {
+    match list.get(0).map(|v| &*v.mal_type) {
+        Some(Symbol(ref name)) => name == "splice-unquote" && list.len() == 2,
+        _ => false,
+    }
+}
+
+fn new_call(symbol: &str, args: Vec<MalValue>) -> MalValue {
+    let mut list = Vec::with_capacity(args.len() + 1);
+    list.push(MalValue::new(Symbol(symbol.to_string())));
+    list.extend(args);
+
+    MalValue::new_list(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use malrs::types::MalError::*;
+
+    #[test]
+    fn test_empty_program() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("", &mut env), Err(EmptyProgram));
+    }
+
+    #[test]
+    fn test_empty_list() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("()", &mut env), Ok("()".to_string()));
+    }
+
+    #[test]
+    fn test_nested_arithmetic() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(+ 2 (* 3 4))", &mut env), Ok("14".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_let() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(let* (c 2) (+ 3 c))", &mut env), Ok("5".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_do() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(do 1 :s2 3 :s4)", &mut env), Ok(":s4".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_if() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(if true 1 2)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(if false 1 2)", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("(if false :a)", &mut env), Ok("nil".to_string()));
+    }
+
+    #[test]
+    fn test_tco_deep_recursion() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! sum-to (fn* (n acc) (if (= n 0) acc (sum-to (- n 1) (+ acc n)))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(sum-to 10000 0)", &mut env),
+            Ok("50005000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tco_deep_do_recursion() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(def! count-to (fn* (n) (do (if (= n 10000) n (count-to (+ n 1))))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(rep("(count-to 0)", &mut env), Ok("10000".to_string()));
+    }
+
+    #[test]
+    fn test_quote() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(quote (1 2 3))", &mut env), Ok("(1 2 3)".to_string()));
+        assert_eq!(rep("(quote a)", &mut env), Ok("a".to_string()));
+    }
+
+    #[test]
+    fn test_quasiquote_unquote() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(quasiquote (1 (unquote (+ 1 1)) 3))", &mut env),
+            Ok("(1 2 3)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quasiquote_splice_unquote() {
+        let mut env = create_root_env(&[]);
+        rep("(def! lst (quote (2 3)))", &mut env).unwrap();
+        assert_eq!(
+            rep("(quasiquote (1 (splice-unquote lst) 4))", &mut env),
+            Ok("(1 2 3 4)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quasiquote_symbol_is_quoted() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("(quasiquote a)", &mut env), Ok("a".to_string()));
+    }
+
+    #[test]
+    fn test_defmacro_unless() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(defmacro! unless (fn* (pred a b) (quasiquote (if (unquote pred) (unquote b) (unquote a)))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(rep("(unless false 7 8)", &mut env), Ok("7".to_string()));
+        assert_eq!(rep("(unless true 7 8)", &mut env), Ok("8".to_string()));
+    }
+
+    #[test]
+    fn test_macroexpand() {
+        let mut env = create_root_env(&[]);
+        rep(
+            "(defmacro! unless (fn* (pred a b) (quasiquote (if (unquote pred) (unquote b) (unquote a)))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(macroexpand (unless PRED A B))", &mut env),
+            Ok("(if PRED B A)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_try_catch_thrown_map() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep(
+                "(try* (throw {:msg \"x\"}) (catch* e (get e :msg)))",
+                &mut env
+            ),
+            Ok("\"x\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_try_catch_native_error() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(try* (abc 1 2) (catch* e e))", &mut env),
+            Ok("\"'abc' not found\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_try_without_error() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(
+            rep("(try* (+ 1 2) (catch* e e))", &mut env),
+            Ok("3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_atom_deref_and_swap() {
+        let mut env = create_root_env(&[]);
+        rep("(def! a (atom 2))", &mut env).unwrap();
+        assert_eq!(rep("(atom? a)", &mut env), Ok("true".to_string()));
+        assert_eq!(rep("(deref a)", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("@a", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("(swap! a + 3)", &mut env), Ok("5".to_string()));
+        assert_eq!(rep("@a", &mut env), Ok("5".to_string()));
+    }
+
+    #[test]
+    fn test_reset_atom() {
+        let mut env = create_root_env(&[]);
+        rep("(def! a (atom 1))", &mut env).unwrap();
+        assert_eq!(rep("(reset! a 10)", &mut env), Ok("10".to_string()));
+        assert_eq!(rep("@a", &mut env), Ok("10".to_string()));
+    }
+
+    #[test]
+    fn test_swap_with_mal_func() {
+        let mut env = create_root_env(&[]);
+        rep("(def! a (atom 5))", &mut env).unwrap();
+        rep("(def! double (fn* (n) (* n 2)))", &mut env).unwrap();
+        assert_eq!(rep("(swap! a double)", &mut env), Ok("10".to_string()));
+    }
+
+    #[test]
+    fn test_argv_defaults_to_empty_list() {
+        let mut env = create_root_env(&[]);
+        assert_eq!(rep("*ARGV*", &mut env), Ok("()".to_string()));
+    }
+
+    #[test]
+    fn test_argv_with_script_args() {
+        let mut env = create_root_env(&[
+            "script.mal".to_string(),
+            "a".to_string(),
+            "b".to_string(),
+        ]);
+        assert_eq!(rep("*ARGV*", &mut env), Ok("(\"a\" \"b\")".to_string()));
+    }
+}
@@ -0,0 +1,382 @@
+use malrs::core::ns;
+use malrs::env::Env;
+use malrs::printer::pr_str;
+use malrs::reader::read_str;
+use malrs::readline::Readline;
+use malrs::types::MalValueType::{List, MalFunc, Map, Nil, RustFunc, Symbol, Vector};
+use malrs::types::{MalError, MalMap, MalResult, MalValue, MalVector};
+use malrs::types::{MalList, MalValueType};
+use std::iter::once;
+
+fn main() {
+    let mut env = create_root_env();
+    let mut readline = Readline::new();
+
+    rep("(def! not (fn* (a) (if a false true)))", &mut env).unwrap();
+
+    loop {
+        match readline.readline() {
+            None => break,
+            Some(line) => {
+                if !line.is_empty() {
+                    match rep(&line, &mut env) {
+                        Ok(result) => println!("{}", result),
+                        Err(MalError::EmptyProgram) => {}
+                        Err(mal_error) => println!("Error! {}", mal_error),
+                    }
+                }
+            }
+        }
+    }
+
+    readline.save_history();
+}
+
+fn create_root_env() -> Env {
+    let mut env = Env::new();
+
+    for (name, val) in ns(&env) {
+        env.set(name, val);
+    }
+
+    env
+}
+
+fn rep(s: &str, env: &mut Env) -> Result<String, MalError> {
+    let read_val = read(s)?;
+    let eval_val = eval(&read_val, env)?;
+    Ok(print(&eval_val))
+}
+
+fn read(s: &str) -> MalResult {
+    read_str(s)
+}
+
+fn eval(ast: &MalValue, env: &mut Env) -> MalResult {
+    let mut ast = ast.clone();
+    let mut env = env.clone();
+
+    'tco: loop {
+        match *ast.mal_type {
+            List(ref mal_list) if mal_list.vec.is_empty() => return Ok(ast.clone()),
+            List(MalList { vec: ref list, .. }) => {
+                let first_arg = &list[0];
+
+                match *first_arg.mal_type {
+                    Symbol(ref name) if name == "def!" => {
+                        return apply_special_form_def(&list[1..], &mut env)
+                    }
+                    Symbol(ref name) if name == "let*" => {
+                        let (inner_env, body) = apply_special_form_let(&list[1..], &env)?;
+                        env = inner_env;
+                        ast = body;
+                        continue 'tco;
+                    }
+                    Symbol(ref name) if name == "fn*" => {
+                        return apply_special_form_fn(&list[1..], &env)
+                    }
+                    Symbol(ref name) if name == "do" => {
+                        ast = apply_special_form_do(&list[1..], &mut env)?;
+                        continue 'tco;
+                    }
+                    Symbol(ref name) if name == "if" => {
+                        match apply_special_form_if(&list[1..], &mut env)? {
+                            Some(branch) => {
+                                ast = branch;
+                                continue 'tco;
+                            }
+                            None => return Ok(MalValue::nil()),
+                        }
+                    }
+                    _ => {
+                        let evaluated_list_ast = eval_ast(&ast, &mut env)?;
+                        match *evaluated_list_ast.mal_type {
+                            List(MalList {
+                                vec: ref evaluated_list,
+                                ..
+                            }) => match *evaluated_list
+                                .get(0)
+                                .expect("Evaluation of non-empty list resulted in empty list.")
+                                .mal_type
+                            {
+                                RustFunc(ref rust_function) => {
+                                    return (rust_function.func)(
+                                        &evaluated_list[1..],
+                                        &mut rust_function.env.clone(),
+                                    )
+                                }
+                                MalFunc(ref mal_func) => {
+                                    let func_env = Env::with_binds(
+                                        Some(&mal_func.outer_env),
+                                        &mal_func.parameters,
+                                        &evaluated_list[1..],
+                                    )?;
+                                    env = func_env;
+                                    ast = mal_func.body.clone();
+                                    continue 'tco;
+                                }
+                                _ => {
+                                    return Err(MalError::Evaluation(
+                                        "First element of a list must evaluate to a function."
+                                            .to_string(),
+                                    ))
+                                }
+                            },
+                            _ => panic!(
+                                "Evaluation of list resulted in non-list: {:?}",
+                                evaluated_list_ast
+                            ),
+                        }
+                    }
+                }
+            }
+            _ => return eval_ast(&ast, &mut env),
+        }
+    }
+}
+
+fn print(mal_val: &MalValue) -> String {
+    pr_str(mal_val, true)
+}
+
+fn eval_ast(ast: &MalValue, env: &mut Env) -> MalResult {
+    match *ast.mal_type {
+        Symbol(ref s) => env.get(&s),
+        List(MalList { vec: ref list, .. }) => Ok(MalValue::new_list(eval_ast_seq(list, env)?)),
+        Vector(ref mal_vec) => Ok(MalValue::new_vector(eval_ast_seq(&mal_vec.vec, env)?)),
+        Map(ref mal_map) => eval_map(mal_map, env),
+        _ => Ok(ast.clone()),
+    }
+}
+
+fn eval_ast_seq(seq: &[MalValue], env: &mut Env) -> Result<Vec<MalValue>, MalError> {
+    seq.iter().map(|mal_val| eval(mal_val, env)).collect()
+}
+
+fn eval_map(mal_map: &MalMap, env: &mut Env) -> MalResult {
+    let map_args: Result<Vec<_>, _> = mal_map
+        .iter()
+        .flat_map(|(key, val)| once(Ok(key.clone())).chain(once(eval(val, env))))
+        .collect();
+
+    Ok(MalValue::new(Map(MalMap::from_arguments(
+        map_args?.as_slice(),
+    )?)))
+}
+
+fn apply_special_form_def(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "def! expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let arg1 = if let Symbol(ref symbol) = *args[0].mal_type {
+        Ok(symbol)
+    } else {
+        Err(MalError::SpecialForm(
+            "def! first argument must be a valid symbol name".to_string(),
+        ))
+    }?;
+
+    let arg2 = eval(&args[1], env)?;
+
+    env.set(arg1.as_str(), arg2.clone());
+
+    Ok(arg2)
+}
+
+/// Builds the inner `Env` for a `let*` form and returns it along with the body
+/// to evaluate in tail position, instead of evaluating the body itself.
+fn apply_special_form_let(args: &[MalValue], env: &Env) -> Result<(Env, MalValue), MalError> {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "let* expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let bindings = match *args[0].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings.as_slice()),
+        _ => Err(MalError::SpecialForm(
+            "let* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    if bindings.len() % 2 != 0 {
+        return Err(MalError::SpecialForm(
+            "let* bindings list must have an even number of elements".to_string(),
+        ));
+    }
+
+    let mut inner_env = Env::with_outer_env(env);
+
+    for i in (0..bindings.len()).step_by(2) {
+        let binding_name = if let Symbol(ref symbol) = *bindings[i].mal_type {
+            Ok(symbol)
+        } else {
+            Err(MalError::SpecialForm(
+                "let* odd numbered elements of binding list must be valid symbol names".to_string(),
+            ))
+        }?;
+
+        let binding_expr = eval(&bindings[i + 1], &mut inner_env)?;
+
+        inner_env.set(binding_name.as_str(), binding_expr);
+    }
+
+    Ok((inner_env, args[1].clone()))
+}
+
+fn apply_special_form_fn(args: &[MalValue], env: &Env) -> MalResult {
+    if args.len() != 2 {
+        return Err(MalError::SpecialForm(format!(
+            "fn* expected 2 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let bindings = match *args[0].mal_type {
+        List(MalList {
+            vec: ref bindings, ..
+        })
+        | Vector(MalVector {
+            vec: ref bindings, ..
+        }) => Ok(bindings.as_slice()),
+        _ => Err(MalError::SpecialForm(
+            "fn* first argument must be a list or a vector".to_string(),
+        )),
+    }?;
+
+    let parameters: Result<Vec<String>, _> = bindings
+        .iter()
+        .map(|val| {
+            if let Symbol(ref symbol) = *val.mal_type {
+                Ok(symbol.clone())
+            } else {
+                Err(MalError::SpecialForm(
+                    "fn*! first argument must be a sequence of valid symbol names".to_string(),
+                ))
+            }
+        })
+        .collect();
+
+    Ok(MalValue::new_mal_func(
+        args[1].clone(),
+        parameters?,
+        env.clone(),
+    ))
+}
+
+/// Evaluates all but the last form of a `do` body and returns the last form
+/// unevaluated, so the caller can continue the trampoline loop in tail position.
+fn apply_special_form_do(args: &[MalValue], env: &mut Env) -> MalResult {
+    if args.is_empty() {
+        return Ok(MalValue::nil());
+    }
+
+    for expr in &args[..args.len() - 1] {
+        eval(expr, env)?;
+    }
+
+    Ok(args[args.len() - 1].clone())
+}
+
+/// Evaluates the `if` condition and returns the branch to evaluate in tail
+/// position, or `None` when there's no else-branch and the condition is falsy.
+fn apply_special_form_if(args: &[MalValue], env: &mut Env) -> Result<Option<MalValue>, MalError> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(MalError::SpecialForm(format!(
+            "if expected 2 or 3 arguments, got {}",
+            args.len()
+        )));
+    }
+
+    let test_result = eval(&args[0], env)?;
+
+    match *test_result.mal_type {
+        MalValueType::False | Nil => {
+            if args.len() == 3 {
+                Ok(Some(args[2].clone()))
+            } else {
+                Ok(None)
+            }
+        }
+        _ => Ok(Some(args[1].clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use malrs::types::MalError::*;
+
+    #[test]
+    fn test_empty_program() {
+        let mut env = create_root_env();
+        assert_eq!(rep("", &mut env), Err(EmptyProgram));
+    }
+
+    #[test]
+    fn test_empty_list() {
+        let mut env = create_root_env();
+        assert_eq!(rep("()", &mut env), Ok("()".to_string()));
+    }
+
+    #[test]
+    fn test_nested_arithmetic() {
+        let mut env = create_root_env();
+        assert_eq!(rep("(+ 2 (* 3 4))", &mut env), Ok("14".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_let() {
+        let mut env = create_root_env();
+        assert_eq!(rep("(let* (c 2) (+ 3 c))", &mut env), Ok("5".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_do() {
+        let mut env = create_root_env();
+        assert_eq!(rep("(do 1 :s2 3 :s4)", &mut env), Ok(":s4".to_string()));
+    }
+
+    #[test]
+    fn test_special_form_if() {
+        let mut env = create_root_env();
+        assert_eq!(rep("(if true 1 2)", &mut env), Ok("1".to_string()));
+        assert_eq!(rep("(if false 1 2)", &mut env), Ok("2".to_string()));
+        assert_eq!(rep("(if false :a)", &mut env), Ok("nil".to_string()));
+    }
+
+    #[test]
+    fn test_tco_deep_recursion() {
+        let mut env = create_root_env();
+        rep(
+            "(def! sum-to (fn* (n acc) (if (= n 0) acc (sum-to (- n 1) (+ acc n)))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            rep("(sum-to 10000 0)", &mut env),
+            Ok("50005000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tco_deep_do_recursion() {
+        let mut env = create_root_env();
+        rep(
+            "(def! count-to (fn* (n) (do (if (= n 10000) n (count-to (+ n 1))))))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(rep("(count-to 0)", &mut env), Ok("10000".to_string()));
+    }
+}
@@ -2,14 +2,16 @@ use crate::env::Env;
 use crate::printer::pr_str;
 use crate::reader::read_str;
 use crate::types::MalValueType::{
-    Atom, False, Keyword, List, MalFunc, Map, Nil, Number, RustFunc, Str, Symbol, True, Vector,
+    Atom, False, Int, Keyword, List, MalFunc, Map, Nil, Number, RustFunc, Str, Symbol, True, Vector,
 };
 use crate::types::{MalError, MalList, MalMap, MalResult, MalValue, MalVector};
+use lazy_static::lazy_static;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use std::error::Error;
 use std::fs;
 use std::slice;
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
@@ -18,6 +20,10 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("-", MalValue::new_rust_func(subtract, env)),
         ("*", MalValue::new_rust_func(multiply, env)),
         ("/", MalValue::new_rust_func(divide, env)),
+        ("mod", MalValue::new_rust_func(modulo, env)),
+        ("quot", MalValue::new_rust_func(quotient, env)),
+        ("float", MalValue::new_rust_func(float, env)),
+        ("int", MalValue::new_rust_func(int, env)),
         ("prn", MalValue::new_rust_func(prn, env)),
         ("println", MalValue::new_rust_func(mal_println, env)),
         ("pr-str", MalValue::new_rust_func(mal_pr_str, env)),
@@ -56,6 +62,7 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("apply", MalValue::new_rust_func(apply, env)),
         ("map", MalValue::new_rust_func(map, env)),
         ("vector", MalValue::new_rust_func(vector, env)),
+        ("vec", MalValue::new_rust_func(vec_fn, env)),
         ("vector?", MalValue::new_rust_func(is_vector, env)),
         ("sequential?", MalValue::new_rust_func(is_sequential, env)),
         ("hash-map", MalValue::new_rust_func(hash_map, env)),
@@ -67,6 +74,8 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
         ("keys", MalValue::new_rust_func(keys, env)),
         ("vals", MalValue::new_rust_func(vals, env)),
         ("readline", MalValue::new_rust_func(readline, env)),
+        ("read-history", MalValue::new_rust_func(read_history, env)),
+        ("write-history", MalValue::new_rust_func(write_history, env)),
         ("meta", MalValue::new_rust_func(meta, env)),
         ("with-meta", MalValue::new_rust_func(with_meta, env)),
         ("string?", MalValue::new_rust_func(is_string, env)),
@@ -78,31 +87,21 @@ pub fn ns(env: &Env) -> Vec<(&'static str, MalValue)> {
     ]
 }
 
-static mut EVAL_FUNC: fn(ast: &MalValue, env: &mut Env) -> MalResult = dummy_eval;
-
-fn dummy_eval(_: &MalValue, _: &mut Env) -> MalResult {
-    panic!("core EVAL_FUNC was not set. You must call core::set_eval_func().")
-}
-
-pub fn set_eval_func(func: fn(ast: &MalValue, env: &mut Env) -> MalResult) {
-    unsafe {
-        EVAL_FUNC = func;
-    }
-}
-
-fn core_eval(ast: &MalValue, env: &mut Env) -> MalResult {
-    unsafe { EVAL_FUNC(ast, env) }
-}
-
-fn core_apply(function: &MalValue, args: &[MalValue], _env: &mut Env) -> MalResult {
+/// Applies a `RustFunc` or `MalFunc` value to `args`, regardless of the
+/// calling context. Shared by the builtins below (`apply`, `map`, `swap!`)
+/// and by the `eval` loop's own function-application dispatch. A `MalFunc`
+/// body is evaluated through the `eval` function attached to its own
+/// `outer_env` (see `Env::with_eval_fn`), so this never needs to reach for a
+/// process-wide global.
+pub fn apply_function(function: &MalValue, args: &[MalValue], _env: &mut Env) -> MalResult {
     match *function.mal_type {
         RustFunc(ref rust_function) => {
             Ok((rust_function.func)(&args, &mut rust_function.env.clone())?)
         }
         MalFunc(ref mal_func) => {
-            let mut func_env =
+            let func_env =
                 Env::with_binds(Some(&mal_func.outer_env), &mal_func.parameters, &args)?;
-            core_eval(&mal_func.body, &mut func_env)
+            func_env.eval(&mal_func.body)
         }
         _ => Err(MalError::RustFunction("Expected function.".to_string())),
     }
@@ -135,38 +134,95 @@ fn arg_count_gte(args: &[MalValue], min_args: usize) -> Result<(), MalError> {
 }
 
 fn get_number_arg(arg: &MalValue) -> Result<f64, MalError> {
-    if let Number(n) = *arg.mal_type {
+    match *arg.mal_type {
+        Number(n) => Ok(n),
+        Int(n) => Ok(n as f64),
+        _ => Err(MalError::RustFunction(
+            "Argument must be a number".to_string(),
+        )),
+    }
+}
+
+fn get_int_arg(arg: &MalValue) -> Result<i64, MalError> {
+    if let Int(n) = *arg.mal_type {
         Ok(n)
     } else {
         Err(MalError::RustFunction(
-            "Argument must be a number".to_string(),
+            "Argument must be an integer".to_string(),
         ))
     }
 }
 
 fn add(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a + b)
+    eval_arithmetic_operation(args, |a, b| a + b, |a, b| a + b)
 }
 
 fn subtract(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a - b)
+    eval_arithmetic_operation(args, |a, b| a - b, |a, b| a - b)
 }
 
 fn multiply(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a * b)
+    eval_arithmetic_operation(args, |a, b| a * b, |a, b| a * b)
 }
 
 fn divide(args: &[MalValue], _env: &mut Env) -> MalResult {
-    eval_arithmetic_operation(args, |a, b| a / b)
+    eval_arithmetic_operation(args, |a, b| a / b, |a, b| a / b)
 }
 
-fn eval_arithmetic_operation(args: &[MalValue], op: fn(f64, f64) -> f64) -> MalResult {
+/// Applies `int_op` when both operands are `Int`, promoting to `Number` and
+/// applying `float_op` as soon as either operand is a `Number`.
+fn eval_arithmetic_operation(
+    args: &[MalValue],
+    int_op: fn(i64, i64) -> i64,
+    float_op: fn(f64, f64) -> f64,
+) -> MalResult {
     arg_count_eq(args, 2)?;
 
-    let arg_1 = get_number_arg(&args[0])?;
-    let arg_2 = get_number_arg(&args[1])?;
+    match (&*args[0].mal_type, &*args[1].mal_type) {
+        (Int(a), Int(b)) => Ok(MalValue::new(Int(int_op(*a, *b)))),
+        (Int(a), Number(b)) => Ok(MalValue::new(Number(float_op(*a as f64, *b)))),
+        (Number(a), Int(b)) => Ok(MalValue::new(Number(float_op(*a, *b as f64)))),
+        (Number(a), Number(b)) => Ok(MalValue::new(Number(float_op(*a, *b)))),
+        _ => Err(MalError::RustFunction(
+            "Arguments must be numbers".to_string(),
+        )),
+    }
+}
+
+fn modulo(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let arg_1 = get_int_arg(&args[0])?;
+    let arg_2 = get_int_arg(&args[1])?;
+
+    Ok(MalValue::new(Int(arg_1 % arg_2)))
+}
+
+fn quotient(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 2)?;
+
+    let arg_1 = get_int_arg(&args[0])?;
+    let arg_2 = get_int_arg(&args[1])?;
+
+    Ok(MalValue::new(Int(arg_1 / arg_2)))
+}
+
+fn float(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
 
-    Ok(MalValue::new(Number(op(arg_1, arg_2))))
+    Ok(MalValue::new(Number(get_number_arg(&args[0])?)))
+}
+
+fn int(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    match *args[0].mal_type {
+        Int(n) => Ok(MalValue::new(Int(n))),
+        Number(n) => Ok(MalValue::new(Int(n as i64))),
+        _ => Err(MalError::RustFunction(
+            "Argument must be a number".to_string(),
+        )),
+    }
 }
 
 fn list(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -240,10 +296,10 @@ fn count(args: &[MalValue], _env: &mut Env) -> MalResult {
 
     match *args[0].mal_type {
         List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
-            Ok(MalValue::new(Number(vec.len() as f64)))
+            Ok(MalValue::new(Int(vec.len() as i64)))
         }
-        Str(ref s) => Ok(MalValue::new(Number(s.len() as f64))),
-        Nil => Ok(MalValue::new(Number(0.))),
+        Str(ref s) => Ok(MalValue::new(Int(s.len() as i64))),
+        Nil => Ok(MalValue::new(Int(0))),
         _ => Err(MalError::RustFunction("Invalid argument".to_string())),
     }
 }
@@ -251,7 +307,7 @@ fn count(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn nth(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 2)?;
 
-    let index = get_number_arg(&args[1])?;
+    let index = get_int_arg(&args[1])?;
 
     if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *args[0].mal_type {
         vec.get(index as usize)
@@ -409,7 +465,7 @@ fn slurp(args: &[MalValue], _env: &mut Env) -> MalResult {
 fn mal_eval(args: &[MalValue], env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    core_eval(&args[0], env)
+    env.eval(&args[0])
 }
 
 fn atom(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -470,7 +526,7 @@ fn swap_atom(args: &[MalValue], env: &mut Env) -> MalResult {
     apply_args.push(atom.borrow().clone());
     apply_args.extend_from_slice(&args[2..]);
 
-    let result = core_apply(&args[1], &apply_args, env)?;
+    let result = apply_function(&args[1], &apply_args, env)?;
 
     atom.replace(result.clone());
     Ok(result)
@@ -538,7 +594,7 @@ fn apply(args: &[MalValue], env: &mut Env) -> MalResult {
         vec.extend_from_slice(&args[1..args.len() - 1]);
         vec.extend_from_slice(&last_args);
 
-        core_apply(&args[0], &vec, env)
+        apply_function(&args[0], &vec, env)
     } else {
         Err(MalError::RustFunction(
             "Invalid argument. Last argument of apply must be a list or vector.".to_string(),
@@ -554,7 +610,7 @@ fn map(args: &[MalValue], env: &mut Env) -> MalResult {
     if let List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) = *args[1].mal_type {
         let result_vec: Result<_, _> = vec
             .iter()
-            .map(|elem| core_apply(function, slice::from_ref(elem), env))
+            .map(|elem| apply_function(function, slice::from_ref(elem), env))
             .collect();
 
         Ok(MalValue::new_list(result_vec?))
@@ -568,12 +624,12 @@ fn map(args: &[MalValue], env: &mut Env) -> MalResult {
 fn symbol(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Str(ref str_val) = *args[0].mal_type {
-        Ok(MalValue::new(Symbol(str_val.clone())))
-    } else {
-        Err(MalError::RustFunction(
+    match *args[0].mal_type {
+        Symbol(_) => Ok(args[0].clone()),
+        Str(ref str_val) => Ok(MalValue::new(Symbol(str_val.clone()))),
+        _ => Err(MalError::RustFunction(
             "Argument must be a string.".to_string(),
-        ))
+        )),
     }
 }
 
@@ -587,15 +643,18 @@ fn is_keyword(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
+/// `Keyword` is already its own `MalValueType` variant here rather than a
+/// sentinel-prefixed string, so the only behavior this (and `symbol`) needs
+/// to add is idempotency on a value that's already the right type.
 fn keyword(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
-    if let Str(ref str_val) = *args[0].mal_type {
-        Ok(MalValue::new(Keyword(str_val.clone())))
-    } else {
-        Err(MalError::RustFunction(
+    match *args[0].mal_type {
+        Keyword(_) => Ok(args[0].clone()),
+        Str(ref str_val) => Ok(MalValue::new(Keyword(str_val.clone()))),
+        _ => Err(MalError::RustFunction(
             "Argument must be a string.".to_string(),
-        ))
+        )),
     }
 }
 
@@ -603,6 +662,19 @@ fn vector(args: &[MalValue], _env: &mut Env) -> MalResult {
     Ok(MalValue::new_vector(Vec::from(args)))
 }
 
+fn vec_fn(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    match *args[0].mal_type {
+        List(MalList { ref vec, .. }) | Vector(MalVector { ref vec, .. }) => {
+            Ok(MalValue::new_vector(vec.clone()))
+        }
+        _ => Err(MalError::RustFunction(
+            "Argument must be a list or vector.".to_string(),
+        )),
+    }
+}
+
 fn is_vector(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
@@ -711,15 +783,24 @@ fn vals(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
+lazy_static! {
+    /// A single process-wide rustyline editor, so the `readline` builtin keeps
+    /// its line history across calls instead of starting fresh every time.
+    static ref READLINE_EDITOR: Mutex<Editor<()>> = Mutex::new(Editor::<()>::new());
+}
+
 fn readline(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
     if let Str(ref prompt) = *args[0].mal_type {
-        let mut editor = Editor::<()>::new();
+        let mut editor = READLINE_EDITOR.lock().unwrap();
 
         let read_result = editor.readline(prompt);
         match read_result {
-            Ok(line) => Ok(MalValue::new(Str(line.trim_end_matches('\n').to_string()))),
+            Ok(line) => {
+                editor.add_history_entry(line.as_str());
+                Ok(MalValue::new(Str(line.trim_end_matches('\n').to_string())))
+            }
             Err(ReadlineError::Eof) => Ok(MalValue::nil()),
             Err(_err) => Err(MalError::RustFunction("Error reading line.".to_string())),
         }
@@ -730,6 +811,42 @@ fn readline(args: &[MalValue], _env: &mut Env) -> MalResult {
     }
 }
 
+fn read_history(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    if let Str(ref path) = *args[0].mal_type {
+        READLINE_EDITOR
+            .lock()
+            .unwrap()
+            .load_history(path)
+            .map_err(|e| MalError::RustFunction(format!("read-history: {}", e)))?;
+
+        Ok(MalValue::nil())
+    } else {
+        Err(MalError::RustFunction(
+            "Argument must be a string.".to_string(),
+        ))
+    }
+}
+
+fn write_history(args: &[MalValue], _env: &mut Env) -> MalResult {
+    arg_count_eq(args, 1)?;
+
+    if let Str(ref path) = *args[0].mal_type {
+        READLINE_EDITOR
+            .lock()
+            .unwrap()
+            .save_history(path)
+            .map_err(|e| MalError::RustFunction(format!("write-history: {}", e)))?;
+
+        Ok(MalValue::nil())
+    } else {
+        Err(MalError::RustFunction(
+            "Argument must be a string.".to_string(),
+        ))
+    }
+}
+
 fn meta(args: &[MalValue], _env: &mut Env) -> MalResult {
     arg_count_eq(args, 1)?;
 
@@ -774,7 +891,7 @@ fn time_ms(args: &[MalValue], _env: &mut Env) -> MalResult {
         .map_err(|_| MalError::RustFunction("Could not calculate the current time.".to_string()))?
         .as_millis();
 
-    Ok(MalValue::new(Number(millis as f64)))
+    Ok(MalValue::new(Int(millis as i64)))
 }
 
 fn seq(args: &[MalValue], _env: &mut Env) -> MalResult {
@@ -1,35 +1,152 @@
-use std::collections::HashMap;
-use types::{MalError, MalResult, MalValue};
+use crate::types::{MalError, MalResult, MalValue};
+use fnv::FnvHashMap;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug)]
-struct Env {
-    data: HashMap<String, MalValue>,
+const VARIADIC_MARKER: &str = "&";
+
+/// The `eval` entry point of the interpreter that owns this `Env`, captured so
+/// that builtins (`eval`, `apply`, `map`, `swap!`, ...) can call back into it
+/// without going through a process-wide global. Cloning an `Env` cheaply
+/// shares the same handle, so every scope descended from a root `Env` created
+/// with [`Env::with_eval_fn`] can reach the same interpreter.
+pub type EvalFn = Rc<dyn Fn(&MalValue, &mut Env) -> MalResult>;
+
+#[derive(Clone)]
+pub struct Env {
+    data: Rc<RefCell<FnvHashMap<String, MalValue>>>,
+    outer: Option<Box<Env>>,
+    eval_fn: Option<EvalFn>,
+}
+
+impl PartialEq for Env {
+    fn eq(&self, other: &Env) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Env")
+            .field("data", &self.data)
+            .field("outer", &self.outer)
+            .finish()
+    }
 }
 
 impl Env {
-    fn new() -> Env {
+    pub fn new() -> Env {
+        Env {
+            data: Rc::new(RefCell::new(FnvHashMap::default())),
+            outer: None,
+            eval_fn: None,
+        }
+    }
+
+    pub fn with_outer_env(outer: &Env) -> Env {
         Env {
-            data: HashMap::new(),
+            data: Rc::new(RefCell::new(FnvHashMap::default())),
+            outer: Some(Box::new(outer.clone())),
+            eval_fn: outer.eval_fn.clone(),
         }
     }
 
-    fn set(&mut self, symbol_key: &str, val: MalValue) {
-        self.data.insert(symbol_key.to_string(), val);
+    /// Attaches the interpreter's `eval` entry point to this `Env`, so that
+    /// it (and every child scope derived from it) can call back into `eval`
+    /// via [`Env::eval`] instead of relying on a global.
+    pub fn with_eval_fn(mut self, eval_fn: EvalFn) -> Env {
+        self.eval_fn = Some(eval_fn);
+        self
+    }
+
+    /// Evaluates `ast` using the interpreter's `eval` function captured by
+    /// this `Env` (or one of its ancestors). Panics if no `eval` function was
+    /// ever attached, which would be a bug in how the root `Env` was built.
+    pub fn eval(&self, ast: &MalValue) -> MalResult {
+        let eval_fn = self
+            .eval_fn
+            .clone()
+            .expect("Env::eval called before an eval function was attached with with_eval_fn()");
+
+        eval_fn(ast, &mut self.clone())
+    }
+
+    /// Builds a new child `Env` binding `binds` to `exprs`. A `&` in `binds`
+    /// marks the start of a variadic rest parameter: everything before it is
+    /// bound positionally, and everything from that point in `exprs` onward
+    /// is collected into a list bound to the symbol following `&`.
+    pub fn with_binds(
+        outer: Option<&Env>,
+        binds: &[String],
+        exprs: &[MalValue],
+    ) -> Result<Env, MalError> {
+        let mut env = match outer {
+            Some(outer) => Env::with_outer_env(outer),
+            None => Env::new(),
+        };
+
+        match binds.iter().position(|bind| bind == VARIADIC_MARKER) {
+            Some(amp_pos) => {
+                if exprs.len() < amp_pos {
+                    return Err(MalError::Evaluation(format!(
+                        "Expected at least {} argument{}, got {}",
+                        amp_pos,
+                        if amp_pos == 1 { "" } else { "s" },
+                        exprs.len()
+                    )));
+                }
+
+                for (bind, expr) in binds[..amp_pos].iter().zip(exprs) {
+                    env.set(bind, expr.clone());
+                }
+
+                let rest_name = binds.get(amp_pos + 1).ok_or_else(|| {
+                    MalError::SpecialForm(
+                        "'&' in a parameter list must be followed by a binding name".to_string(),
+                    )
+                })?;
+
+                env.set(rest_name, MalValue::new_list(exprs[amp_pos..].to_vec()));
+            }
+            None => {
+                if binds.len() != exprs.len() {
+                    return Err(MalError::Evaluation(format!(
+                        "Expected {} argument{}, got {}",
+                        binds.len(),
+                        if binds.len() == 1 { "" } else { "s" },
+                        exprs.len()
+                    )));
+                }
+
+                for (bind, expr) in binds.iter().zip(exprs) {
+                    env.set(bind, expr.clone());
+                }
+            }
+        }
+
+        Ok(env)
     }
 
-    fn get(&self, symbol_key: &str) -> MalResult {
-        self.data
-            .get(symbol_key)
-            .map(|val| val.clone())
-            .ok_or_else(|| MalError::UndefinedSymbol(symbol_key.to_string()))
+    pub fn set(&mut self, symbol_key: &str, val: MalValue) {
+        self.data.borrow_mut().insert(symbol_key.to_string(), val);
+    }
+
+    pub fn get(&self, symbol_key: &str) -> MalResult {
+        match self.data.borrow().get(symbol_key) {
+            Some(val) => Ok(val.clone()),
+            None => match &self.outer {
+                Some(outer) => outer.get(symbol_key),
+                None => Err(MalError::UndefinedSymbol(symbol_key.to_string())),
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use env::Env;
-    use types::MalValueType::Str;
+    use crate::types::MalValueType::{Number, Str};
 
     #[test]
     fn test_get_from_empty_env() {
@@ -50,4 +167,61 @@ mod tests {
 
         assert_eq!(env.get("sym"), Ok(val));
     }
+
+    #[test]
+    fn test_get_falls_back_to_outer_env() {
+        let mut outer = Env::new();
+        outer.set("sym", MalValue::new(Number(1.)));
+
+        let inner = Env::with_outer_env(&outer);
+
+        assert_eq!(inner.get("sym"), Ok(MalValue::new(Number(1.))));
+    }
+
+    #[test]
+    fn test_inner_env_shadows_outer_env() {
+        let mut outer = Env::new();
+        outer.set("sym", MalValue::new(Number(1.)));
+
+        let mut inner = Env::with_outer_env(&outer);
+        inner.set("sym", MalValue::new(Number(2.)));
+
+        assert_eq!(inner.get("sym"), Ok(MalValue::new(Number(2.))));
+        assert_eq!(outer.get("sym"), Ok(MalValue::new(Number(1.))));
+    }
+
+    #[test]
+    fn test_with_binds_variadic() {
+        let env = Env::with_binds(
+            None,
+            &["a".to_string(), "&".to_string(), "rest".to_string()],
+            &[
+                MalValue::new(Number(1.)),
+                MalValue::new(Number(2.)),
+                MalValue::new(Number(3.)),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(env.get("a"), Ok(MalValue::new(Number(1.))));
+        assert_eq!(
+            env.get("rest"),
+            Ok(MalValue::new_list(vec![
+                MalValue::new(Number(2.)),
+                MalValue::new(Number(3.)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_with_binds_variadic_with_no_extra_args() {
+        let env = Env::with_binds(
+            None,
+            &["&".to_string(), "rest".to_string()],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(env.get("rest"), Ok(MalValue::new_list(Vec::new())));
+    }
 }
@@ -0,0 +1,244 @@
+use crate::types::MalTokenType::*;
+use crate::types::{MalError, MalMap, MalResult, MalToken, MalTokenType, MalValue, MalValueType};
+
+pub fn read_str(input: &str) -> MalResult {
+    let tokens = tokenize(input)?;
+
+    if tokens.is_empty() {
+        return Err(MalError::EmptyProgram);
+    }
+
+    let mut reader = Reader { tokens, pos: 0 };
+    reader.read_form()
+}
+
+struct Reader {
+    tokens: Vec<MalToken>,
+    pos: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<&MalTokenType> {
+        self.tokens.get(self.pos).map(|token| &token.token_type)
+    }
+
+    fn next(&mut self) -> Result<MalTokenType, MalError> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| {
+            MalError::Parser("unexpected end of input".to_string())
+        })?;
+        self.pos += 1;
+        Ok(match &token.token_type {
+            LParen => LParen,
+            RParen => RParen,
+            LCurly => LCurly,
+            RCurly => RCurly,
+            LBracket => LBracket,
+            RBracket => RBracket,
+            AtSign => AtSign,
+            SingleQuote => SingleQuote,
+            BackTick => BackTick,
+            Tilde => Tilde,
+            TildeAtSign => TildeAtSign,
+            Nil => Nil,
+            True => True,
+            False => False,
+            Int(n) => Int(*n),
+            Number(n) => Number(*n),
+            Symbol(s) => Symbol(s.clone()),
+            Str(s) => Str(s.clone()),
+            Keyword(s) => Keyword(s.clone()),
+        })
+    }
+
+    fn read_form(&mut self) -> MalResult {
+        match self.peek() {
+            Some(LParen) => self.read_seq(RParen, MalValue::new_list),
+            Some(LBracket) => self.read_seq(RBracket, MalValue::new_vector),
+            Some(LCurly) => {
+                let elements = self.read_raw_seq(RCurly)?;
+                Ok(MalValue::new(MalValueType::Map(MalMap::from_arguments(
+                    &elements,
+                )?)))
+            }
+            Some(SingleQuote) => self.read_wrapped("quote"),
+            Some(BackTick) => self.read_wrapped("quasiquote"),
+            Some(Tilde) => self.read_wrapped("unquote"),
+            Some(TildeAtSign) => self.read_wrapped("splice-unquote"),
+            Some(AtSign) => self.read_wrapped("deref"),
+            Some(RParen) | Some(RBracket) | Some(RCurly) => Err(MalError::Parser(
+                "unexpected closing delimiter".to_string(),
+            )),
+            Some(_) => self.read_atom(),
+            None => Err(MalError::Parser("unexpected end of input".to_string())),
+        }
+    }
+
+    fn read_wrapped(&mut self, special_form: &str) -> MalResult {
+        self.next()?;
+        let form = self.read_form()?;
+        Ok(MalValue::new_list(vec![
+            MalValue::new(MalValueType::Symbol(special_form.to_string())),
+            form,
+        ]))
+    }
+
+    fn read_seq(
+        &mut self,
+        closing: MalTokenType,
+        build: fn(Vec<MalValue>) -> MalValue,
+    ) -> MalResult {
+        Ok(build(self.read_raw_seq(closing)?))
+    }
+
+    fn read_raw_seq(&mut self, closing: MalTokenType) -> Result<Vec<MalValue>, MalError> {
+        self.next()?;
+        let mut elements = Vec::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(MalError::Parser("expected closing delimiter, got EOF".to_string())),
+                Some(token) if *token == closing => {
+                    self.next()?;
+                    return Ok(elements);
+                }
+                Some(_) => elements.push(self.read_form()?),
+            }
+        }
+    }
+
+    fn read_atom(&mut self) -> MalResult {
+        Ok(match self.next()? {
+            Nil => MalValue::nil(),
+            True => MalValue::new_boolean(true),
+            False => MalValue::new_boolean(false),
+            Int(n) => MalValue::new(MalValueType::Int(n)),
+            Number(n) => MalValue::new(MalValueType::Number(n)),
+            Symbol(s) => MalValue::new(MalValueType::Symbol(s)),
+            Str(s) => MalValue::new(MalValueType::Str(s)),
+            Keyword(s) => MalValue::new(MalValueType::Keyword(s)),
+            _ => return Err(MalError::Parser("expected an atom".to_string())),
+        })
+    }
+}
+
+/// Splits `input` into tokens, classifying each bare (unquoted) word as
+/// `Nil`/`True`/`False` for `nil`/`true`/`false`, `Keyword` when it starts
+/// with `:`, `Int` when it's a numeric literal with no `.`/exponent, `Number`
+/// when it has one, and `Symbol` otherwise.
+fn tokenize(input: &str) -> Result<Vec<MalToken>, MalError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' | '\n' | '\r' | ',' => {
+                chars.next();
+            }
+            ';' => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' => push_and_advance(&mut tokens, &mut chars, LParen),
+            ')' => push_and_advance(&mut tokens, &mut chars, RParen),
+            '[' => push_and_advance(&mut tokens, &mut chars, LBracket),
+            ']' => push_and_advance(&mut tokens, &mut chars, RBracket),
+            '{' => push_and_advance(&mut tokens, &mut chars, LCurly),
+            '}' => push_and_advance(&mut tokens, &mut chars, RCurly),
+            '\'' => push_and_advance(&mut tokens, &mut chars, SingleQuote),
+            '`' => push_and_advance(&mut tokens, &mut chars, BackTick),
+            '@' => push_and_advance(&mut tokens, &mut chars, AtSign),
+            '~' => {
+                chars.next();
+                if chars.peek() == Some(&'@') {
+                    chars.next();
+                    tokens.push(MalToken::new(TildeAtSign));
+                } else {
+                    tokens.push(MalToken::new(Tilde));
+                }
+            }
+            '"' => tokens.push(MalToken::new(Str(read_string_literal(&mut chars)?))),
+            _ => tokens.push(read_raw_token(&mut chars)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn push_and_advance(
+    tokens: &mut Vec<MalToken>,
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    token_type: MalTokenType,
+) {
+    chars.next();
+    tokens.push(MalToken::new(token_type));
+}
+
+fn read_string_literal(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<String, MalError> {
+    chars.next();
+    let mut value = String::new();
+
+    loop {
+        match chars.next() {
+            None => return Err(MalError::Tokenizer("unterminated string".to_string())),
+            Some('"') => return Ok(value),
+            Some('\\') => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some(other) => value.push(other),
+                None => return Err(MalError::Tokenizer("unterminated string".to_string())),
+            },
+            Some(other) => value.push(other),
+        }
+    }
+}
+
+fn read_raw_token(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> MalToken {
+    let mut raw = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || ",()[]{}'`~@;\"".contains(c) {
+            break;
+        }
+        raw.push(c);
+        chars.next();
+    }
+
+    MalToken::new(classify_raw_token(&raw))
+}
+
+fn classify_raw_token(raw: &str) -> MalTokenType {
+    match raw {
+        "nil" => Nil,
+        "true" => True,
+        "false" => False,
+        _ => {
+            if let Some(keyword) = raw.strip_prefix(':') {
+                Keyword(keyword.to_string())
+            } else if let Some(token_type) = classify_number(raw) {
+                token_type
+            } else {
+                Symbol(raw.to_string())
+            }
+        }
+    }
+}
+
+fn classify_number(raw: &str) -> Option<MalTokenType> {
+    let digits_start = if raw.starts_with('-') || raw.starts_with('+') {
+        1
+    } else {
+        0
+    };
+
+    if raw.len() <= digits_start || !raw.as_bytes()[digits_start].is_ascii_digit() {
+        return None;
+    }
+
+    if raw.contains('.') || raw.contains('e') || raw.contains('E') {
+        raw.parse::<f64>().ok().map(Number)
+    } else {
+        raw.parse::<i64>().ok().map(Int)
+    }
+}
@@ -1,28 +1,53 @@
 use crate::env::Env;
 use crate::printer::pr_str;
 use crate::types::MalError::*;
+use fnv::FnvHashMap;
 use std::cell::RefCell;
 use std::collections::hash_map;
-use std::collections::HashMap;
 use std::fmt;
-use std::hash::Hash;
-use std::hash::Hasher;
 use std::iter::FusedIterator;
 use std::rc::Rc;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct MalValue {
     pub mal_type: Rc<MalValueType>,
+    meta: Option<Rc<MalValue>>,
     // Possible extra fields: line, column
 }
 
+impl PartialEq for MalValue {
+    fn eq(&self, other: &MalValue) -> bool {
+        self.mal_type == other.mal_type
+    }
+}
+
 impl MalValue {
     pub fn new(mal_type: MalValueType) -> MalValue {
         MalValue {
             mal_type: Rc::new(mal_type),
+            meta: None,
         }
     }
 
+    /// Returns the metadata attached via `with_meta`, or `Nil` if none was
+    /// ever attached. Metadata never participates in `PartialEq`.
+    pub fn get_meta(&self) -> MalResult {
+        match &self.meta {
+            Some(meta) => Ok((**meta).clone()),
+            None => Ok(MalValue::nil()),
+        }
+    }
+
+    /// Returns a shallow copy of `self` sharing the same underlying
+    /// `mal_type`, with its metadata replaced by `meta`. Does not mutate
+    /// `self`.
+    pub fn clone_with_meta(&self, meta: MalValue) -> MalResult {
+        Ok(MalValue {
+            mal_type: Rc::clone(&self.mal_type),
+            meta: Some(Rc::new(meta)),
+        })
+    }
+
     pub fn new_boolean(boolean: bool) -> MalValue {
         if boolean {
             MalValue::new(MalValueType::True)
@@ -93,6 +118,7 @@ pub enum MalValueType {
     Nil,
     True,
     False,
+    Int(i64),
     Number(f64),
     Symbol(String),
     Str(String),
@@ -113,6 +139,9 @@ impl PartialEq for MalValueType {
             (Nil, Nil) => true,
             (True, True) => true,
             (False, False) => true,
+            (Int(l), Int(r)) => l == r,
+            (Int(l), Number(r)) => *l as f64 == *r,
+            (Number(l), Int(r)) => *l == *r as f64,
             (Number(l), Number(r)) => l == r,
             (Symbol(l), Symbol(r)) => l == r,
             (Str(l), Str(r)) => l == r,
@@ -131,33 +160,30 @@ impl PartialEq for MalValueType {
 
 #[derive(Debug, PartialEq)]
 pub struct MalMap {
-    map: HashMap<MalMapKey, MalValue>,
+    map: FnvHashMap<MalMapKey, MalValue>,
 }
 
-#[derive(Clone, Debug)]
-struct MalMapKey {
-    key: String,
-    mal_value: MalValue,
-}
-
-impl PartialEq for MalMapKey {
-    fn eq(&self, other: &MalMapKey) -> bool {
-        self.key == other.key
-    }
+/// A hash map key that distinguishes strings from keywords directly, instead
+/// of encoding the distinction as a prefix character on a shared `String`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum MalMapKey {
+    Str(String),
+    Keyword(String),
 }
 
-impl Eq for MalMapKey {}
-
-impl Hash for MalMapKey {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.key.hash(state);
+impl MalMapKey {
+    fn to_mal_value(&self) -> MalValue {
+        match self {
+            MalMapKey::Str(val) => MalValue::new(MalValueType::Str(val.clone())),
+            MalMapKey::Keyword(val) => MalValue::new(MalValueType::Keyword(val.clone())),
+        }
     }
 }
 
 impl MalMap {
     pub fn new() -> MalMap {
         MalMap {
-            map: HashMap::new(),
+            map: FnvHashMap::default(),
         }
     }
 
@@ -168,24 +194,18 @@ impl MalMap {
             ));
         }
 
-        let mut map = HashMap::with_capacity(arguments.len() % 2);
+        let mut map = FnvHashMap::with_capacity_and_hasher(arguments.len() / 2, Default::default());
 
         for i in (0..arguments.len()).step_by(2) {
             let key = match *arguments[i].mal_type {
-                MalValueType::Str(ref val) => Ok(format!("s{}", val)),
-                MalValueType::Keyword(ref val) => Ok(format!("k{}", val)),
+                MalValueType::Str(ref val) => Ok(MalMapKey::Str(val.clone())),
+                MalValueType::Keyword(ref val) => Ok(MalMapKey::Keyword(val.clone())),
                 _ => Err(MalError::Parser(
                     "hash map keys must be strings or keywords".to_string(),
                 )),
             }?;
 
-            map.insert(
-                MalMapKey {
-                    key,
-                    mal_value: arguments[i].clone(),
-                },
-                arguments[i + 1].clone(),
-            );
+            map.insert(key, arguments[i + 1].clone());
         }
 
         Ok(MalMap { map })
@@ -210,13 +230,13 @@ pub struct MalMapIter<'a> {
 }
 
 impl<'a> Iterator for MalMapIter<'a> {
-    type Item = (&'a MalValue, &'a MalValue);
+    type Item = (MalValue, &'a MalValue);
 
     #[inline]
-    fn next(&mut self) -> Option<(&'a MalValue, &'a MalValue)> {
+    fn next(&mut self) -> Option<(MalValue, &'a MalValue)> {
         let inner_next = self.inner.next();
 
-        inner_next.map(|(key, val)| (&key.mal_value, val))
+        inner_next.map(|(key, val)| (key.to_mal_value(), val))
     }
 
     #[inline]
@@ -307,6 +327,9 @@ impl MalToken {
     }
 }
 
+/// The tokenizer emits `Int` for a numeric literal with no `.` or exponent,
+/// and falls back to `Number` otherwise, so that callers can tell exact
+/// integer literals from floats before a single `MalValue` is built.
 #[derive(Debug, PartialEq)]
 pub enum MalTokenType {
     LParen,
@@ -323,6 +346,7 @@ pub enum MalTokenType {
     Nil,
     True,
     False,
+    Int(i64),
     Number(f64),
     Symbol(String),
     Str(String),